@@ -1,3 +1,36 @@
+use std::collections::HashMap;
+
+/// An error produced while parsing or evaluating an expression.
+#[derive(Debug, PartialEq)]
+pub enum CalcError {
+    /// A token cannot be handled where it appears, such as an operator or a
+    /// variable that the bytecode compiler does not support.
+    UnexpectedToken(String),
+    /// An operator ran out of operands on the stack.
+    StackUnderflow,
+    /// The input contained no tokens, or evaluating it left no value.
+    EmptyExpression,
+    /// Evaluation finished with more than one value left on the stack.
+    TrailingOperands,
+    /// An operator was applied to an operand of the wrong type.
+    TypeError(String),
+    /// A variable was referenced before it was assigned.
+    UndefinedVariable(String),
+}
+
+impl std::fmt::Display for CalcError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CalcError::UnexpectedToken(token) => write!(f, "unexpected token: {token}"),
+            CalcError::StackUnderflow => write!(f, "operator is missing operands"),
+            CalcError::EmptyExpression => write!(f, "empty expression"),
+            CalcError::TrailingOperands => write!(f, "expression left extra operands on the stack"),
+            CalcError::TypeError(msg) => write!(f, "type error: {msg}"),
+            CalcError::UndefinedVariable(name) => write!(f, "undefined variable: {name}"),
+        }
+    }
+}
+
 /// Represents an operation in an expression.
 #[derive(Debug, PartialEq)]
 pub enum Op {
@@ -5,6 +38,48 @@ pub enum Op {
     Sub,
     Mul,
     Div,
+    Rem,
+    Pow,
+    FloorDiv,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    And,
+    Or,
+    Not,
+}
+
+impl Op {
+    /// Returns the binding power of the operator; higher binds tighter.
+    pub fn precedence(&self) -> u8 {
+        match self {
+            Op::Or => 1,
+            Op::And => 2,
+            Op::Eq | Op::Ne | Op::Lt | Op::Le | Op::Gt | Op::Ge => 3,
+            Op::Add | Op::Sub => 4,
+            Op::Mul | Op::Div | Op::Rem | Op::FloorDiv => 5,
+            Op::Pow => 6,
+            // `!` is used postfix in the RPN stream, so it must bind looser
+            // than anything to its left for shunting-yard to emit it last.
+            Op::Not => 0,
+        }
+    }
+
+    /// Returns `true` if the operator is left-associative.
+    ///
+    /// Every operator is left-associative for now, but shunting-yard needs
+    /// to ask the question so the rule lives here.
+    pub fn is_left_associative(&self) -> bool {
+        true
+    }
+
+    /// Returns `true` if the operator takes a single operand (`!`).
+    pub fn is_unary(&self) -> bool {
+        matches!(self, Op::Not)
+    }
 }
 
 /// Represents an expression in an arithmetic expression.
@@ -12,155 +87,732 @@ pub enum Op {
 pub enum Expr {
     Num(Num),
     Op(Op),
+    /// A reference to a variable, pushing its current value.
+    Var(String),
+    /// Names the target variable for the next assignment (`$name`).
+    Name(String),
+    /// The assignment operator (`=`): binds the most recently named variable.
+    Assign,
 }
 
 /// Represents a numerical value in an arithmetic expression.
 #[derive(Debug, PartialEq)]
 pub struct Num(f32);
 
+/// A value living on the evaluation stack: either a number or a boolean.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Value {
+    Num(f32),
+    Bool(bool),
+}
+
+impl Value {
+    /// Returns the number held by this value, or a type error otherwise.
+    fn as_num(self) -> Result<f32, CalcError> {
+        match self {
+            Value::Num(n) => Ok(n),
+            Value::Bool(_) => Err(CalcError::TypeError("expected a number".to_string())),
+        }
+    }
+
+    /// Returns the boolean held by this value, or a type error otherwise.
+    fn as_bool(self) -> Result<bool, CalcError> {
+        match self {
+            Value::Bool(b) => Ok(b),
+            Value::Num(_) => Err(CalcError::TypeError("expected a boolean".to_string())),
+        }
+    }
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Num(n) => write!(f, "{n}"),
+            Value::Bool(b) => write!(f, "{b}"),
+        }
+    }
+}
+
 /// Represents a Reverse Polish Notation (RPN) stack.
-pub struct Rpn(Vec<f32>);
+///
+/// Besides the value stack it carries a variable environment and the name of
+/// the variable most recently announced with `$name`, which the next `=`
+/// assigns into.
+pub struct Rpn {
+    stack: Vec<Value>,
+    vars: HashMap<String, f32>,
+    pending: Option<String>,
+}
 
 /// Represents a binary operation in an arithmetic expression.
 pub struct BinOp {
     pub op: Op,
-    pub lhs: Num,
-    pub mhs: Num,
+    pub lhs: Value,
+    pub mhs: Value,
 }
 
 impl BinOp {
-    /// Executes the binary operation and returns the result.
-    pub fn eval(&self) -> f32 {
+    /// Executes the binary operation and returns its typed result.
+    ///
+    /// Arithmetic operators consume two numbers and yield a number,
+    /// comparisons consume two numbers and yield a boolean, and the boolean
+    /// combinators consume two booleans and yield a boolean.
+    pub fn eval(&self) -> Result<Value, CalcError> {
         match self.op {
-            Op::Add => self.lhs.0 + self.mhs.0,
-            Op::Sub => self.lhs.0 - self.mhs.0,
-            Op::Mul => self.lhs.0 * self.mhs.0,
-            Op::Div => self.lhs.0 / self.mhs.0,
+            Op::Add | Op::Sub | Op::Mul | Op::Div | Op::Rem | Op::Pow | Op::FloorDiv => {
+                let lhs = self.lhs.as_num()?;
+                let mhs = self.mhs.as_num()?;
+                let result = match self.op {
+                    Op::Add => lhs + mhs,
+                    Op::Sub => lhs - mhs,
+                    Op::Mul => lhs * mhs,
+                    Op::Div => lhs / mhs,
+                    Op::Rem => lhs % mhs,
+                    Op::Pow => lhs.powf(mhs),
+                    _ => (lhs / mhs).floor(),
+                };
+                Ok(Value::Num(result))
+            }
+            Op::Eq | Op::Ne | Op::Lt | Op::Le | Op::Gt | Op::Ge => {
+                let lhs = self.lhs.as_num()?;
+                let mhs = self.mhs.as_num()?;
+                let result = match self.op {
+                    Op::Eq => lhs == mhs,
+                    Op::Ne => lhs != mhs,
+                    Op::Lt => lhs < mhs,
+                    Op::Le => lhs <= mhs,
+                    Op::Gt => lhs > mhs,
+                    _ => lhs >= mhs,
+                };
+                Ok(Value::Bool(result))
+            }
+            Op::And | Op::Or => {
+                let lhs = self.lhs.as_bool()?;
+                let mhs = self.mhs.as_bool()?;
+                let result = match self.op {
+                    Op::And => lhs && mhs,
+                    _ => lhs || mhs,
+                };
+                Ok(Value::Bool(result))
+            }
+            Op::Not => unreachable!("unary operators are evaluated by Rpn::push"),
         }
     }
 }
 
+impl Default for Rpn {
+    fn default() -> Rpn {
+        Rpn::new()
+    }
+}
+
 impl Rpn {
     /// Creates a new empty RPN stack.
     pub fn new() -> Rpn {
-        Rpn(Vec::new())
+        Rpn {
+            stack: Vec::new(),
+            vars: HashMap::new(),
+            pending: None,
+        }
     }
 
-    /// Pushes an expression onto the RPN stack.
-    pub fn push(&mut self, expr: Expr) {
+    /// Pushes an expression onto the RPN stack, evaluating operators in place.
+    pub fn push(&mut self, expr: Expr) -> Result<(), CalcError> {
         match expr {
-            Expr::Num(n) => self.0.push(n.0),
+            Expr::Num(n) => self.stack.push(Value::Num(n.0)),
+            Expr::Var(name) => {
+                let value = *self
+                    .vars
+                    .get(&name)
+                    .ok_or(CalcError::UndefinedVariable(name))?;
+                self.stack.push(Value::Num(value));
+            }
+            Expr::Name(name) => self.pending = Some(name),
+            Expr::Assign => {
+                let name = self.pending.take().ok_or(CalcError::StackUnderflow)?;
+                let value = self.stack.pop().ok_or(CalcError::StackUnderflow)?;
+                self.vars.insert(name, value.as_num()?);
+            }
+            Expr::Op(op) if op.is_unary() => {
+                let operand = self.stack.pop().ok_or(CalcError::StackUnderflow)?;
+                self.stack.push(Value::Bool(!operand.as_bool()?));
+            }
             Expr::Op(op) => {
-                let mhs = self.0.pop().unwrap();
-                let lhs = self.0.pop().unwrap();
+                let mhs = self.stack.pop().ok_or(CalcError::StackUnderflow)?;
+                let lhs = self.stack.pop().ok_or(CalcError::StackUnderflow)?;
 
-                let bin_op = BinOp {
-                    op,
-                    lhs: Num(lhs),
-                    mhs: Num(mhs),
-                };
+                let bin_op = BinOp { op, lhs, mhs };
 
-                self.0.push(bin_op.eval());
+                self.stack.push(bin_op.eval()?);
             }
         }
+
+        Ok(())
     }
 }
 
+/// An entry on the shunting-yard operator stack: an operator or a left paren.
+enum Token {
+    Op(Op),
+    LParen,
+}
+
 /// Represents a parser for arithmetic expressions.
 pub struct ExprParser<'a> {
     pub input: &'a str,
 }
 
 impl<'a> ExprParser<'a> {
-    /// Parses the input string into a vector of tokens.
-    pub fn parse(&self) -> Vec<Expr> {
-        if self.input.is_empty() {
-            return vec![Expr::Num(Num(0.0))];
+    /// Parses the input string into a vector of tokens in RPN order.
+    ///
+    /// The input may be written either as space-separated RPN (`1 2 +`) or as
+    /// ordinary infix (`1 + 2 * 3`, `(1 + 2) * 3`). A well-formed RPN stream is
+    /// emitted verbatim so the existing evaluator sees exactly what the user
+    /// wrote; anything else is treated as infix and reordered into RPN with
+    /// Dijkstra's shunting-yard algorithm. Running shunting-yard over already
+    /// RPN input would corrupt chained non-commutative operators (e.g. turn
+    /// `5 3 - 1 -` into `5 3 1 - -`), hence the split.
+    pub fn parse(&self) -> Result<Vec<Expr>, CalcError> {
+        if self.input.trim().is_empty() {
+            return Err(CalcError::EmptyExpression);
         }
 
-        let mut tokens = vec![];
+        // Parentheses are tokens of their own even when written against an operand.
+        let spaced = self.input.replace('(', " ( ").replace(')', " ) ");
+        let tokens: Vec<&str> = spaced.split_whitespace().collect();
 
-        for c in self.input.split(' ') {
+        if Self::is_rpn(&tokens) {
+            self.parse_rpn(&tokens)
+        } else {
+            self.parse_infix(&tokens)
+        }
+    }
+
+    /// Maps an operator token to its [`Op`], or `None` if it is not an operator.
+    fn op_of(token: &str) -> Option<Op> {
+        let op = match token {
+            "+" => Op::Add,
+            "-" => Op::Sub,
+            "*" => Op::Mul,
+            "/" => Op::Div,
+            "%" => Op::Rem,
+            "^" => Op::Pow,
+            "//" => Op::FloorDiv,
+            "==" => Op::Eq,
+            "!=" => Op::Ne,
+            "<" => Op::Lt,
+            "<=" => Op::Le,
+            ">" => Op::Gt,
+            ">=" => Op::Ge,
+            "&&" => Op::And,
+            "||" => Op::Or,
+            "!" => Op::Not,
+            _ => return None,
+        };
+        Some(op)
+    }
+
+    /// Converts a single operand/assignment token into its [`Expr`].
+    ///
+    /// Operators are handled by the caller; this covers numbers, variable
+    /// references, assignment targets (`$name`) and the `=` operator.
+    fn operand_expr(token: &str) -> Expr {
+        match token {
+            "=" => Expr::Assign,
+            _ if token.starts_with('$') => Expr::Name(token[1..].to_string()),
+            _ => match token.parse::<f32>() {
+                Ok(n) => Expr::Num(Num(n)),
+                // A bare, non-numeric token is a variable reference.
+                Err(_) => Expr::Var(token.to_string()),
+            },
+        }
+    }
+
+    /// Returns `true` if the tokens form a well-formed RPN stream.
+    ///
+    /// Simulates the stack depth: operands push one value, binary operators pop
+    /// two and push one, `!` is neutral, and `=` pops one. A stream that never
+    /// underflows and leaves exactly one value is RPN; parentheses or an
+    /// operator applied too early mark it as infix instead.
+    fn is_rpn(tokens: &[&str]) -> bool {
+        let mut depth: i32 = 0;
+
+        for &token in tokens {
+            match token {
+                "(" | ")" => return false,
+                "=" => depth -= 1,
+                _ if token.starts_with('$') => {}
+                _ => match Self::op_of(token) {
+                    Some(op) if op.is_unary() => {
+                        if depth < 1 {
+                            return false;
+                        }
+                    }
+                    Some(_) => {
+                        if depth < 2 {
+                            return false;
+                        }
+                        depth -= 1;
+                    }
+                    None => depth += 1,
+                },
+            }
+
+            if depth < 0 {
+                return false;
+            }
+        }
+
+        depth == 1
+    }
+
+    /// Emits a validated RPN stream in source order, untouched.
+    fn parse_rpn(&self, tokens: &[&str]) -> Result<Vec<Expr>, CalcError> {
+        let mut output = Vec::with_capacity(tokens.len());
+
+        for &token in tokens {
+            match Self::op_of(token) {
+                Some(op) => output.push(Expr::Op(op)),
+                None => output.push(Self::operand_expr(token)),
+            }
+        }
+
+        Ok(output)
+    }
+
+    /// Converts an infix stream into RPN with the shunting-yard algorithm.
+    fn parse_infix(&self, tokens: &[&str]) -> Result<Vec<Expr>, CalcError> {
+        let mut output = vec![];
+        let mut operators: Vec<Token> = vec![];
+
+        for &c in tokens {
             match c {
-                "+" => tokens.push(Expr::Op(Op::Add)),
-                "-" => tokens.push(Expr::Op(Op::Sub)),
-                "*" => tokens.push(Expr::Op(Op::Mul)),
-                "/" => tokens.push(Expr::Op(Op::Div)),
-                _ => tokens.push(Expr::Num(Num(c.parse::<f32>().unwrap()))),
+                "(" => operators.push(Token::LParen),
+                ")" => {
+                    while let Some(top) = operators.pop() {
+                        match top {
+                            Token::LParen => break,
+                            Token::Op(op) => output.push(Expr::Op(op)),
+                        }
+                    }
+                }
+                _ => match Self::op_of(c) {
+                    Some(op) => {
+                        while let Some(Token::Op(top)) = operators.last() {
+                            if top.precedence() > op.precedence()
+                                || (top.precedence() == op.precedence()
+                                    && op.is_left_associative())
+                            {
+                                if let Some(Token::Op(popped)) = operators.pop() {
+                                    output.push(Expr::Op(popped));
+                                }
+                            } else {
+                                break;
+                            }
+                        }
+
+                        operators.push(Token::Op(op));
+                    }
+                    None => output.push(Self::operand_expr(c)),
+                },
+            }
+        }
+
+        while let Some(top) = operators.pop() {
+            if let Token::Op(op) = top {
+                output.push(Expr::Op(op));
             }
         }
 
-        tokens
+        Ok(output)
+    }
+}
+
+/// A register of the abstract machine.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Reg {
+    Ax,
+    Bx,
+    Cx,
+    Dx,
+}
+
+/// The source operand of an ALU instruction: a register or an immediate.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Src {
+    Reg(Reg),
+    Imm(f32),
+}
+
+/// A single instruction for the register machine.
+///
+/// The ALU instructions have `dst op= src` semantics, where `dst` is always a
+/// register and `src` is a register or an immediate.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Instr {
+    Push(Src),
+    Pop(Reg),
+    Add(Src, Reg),
+    Sub(Src, Reg),
+    Mul(Src, Reg),
+    Div(Src, Reg),
+}
+
+/// Lowers an RPN expression into register-machine bytecode.
+///
+/// Each `Num` pushes its immediate; each `Op` pops the two operands into `ax`
+/// and `bx`, folds them with `bx op= ax` (so `Sub`/`Div` compute `lhs - rhs`
+/// and `lhs / rhs`), and pushes the result back so nested subexpressions
+/// compose. Only the four basic arithmetic operators are supported.
+pub fn compile(exprs: &[Expr]) -> Result<Vec<Instr>, CalcError> {
+    let mut code = vec![];
+
+    for expr in exprs {
+        match expr {
+            Expr::Num(n) => code.push(Instr::Push(Src::Imm(n.0))),
+            Expr::Op(op) => {
+                let alu = match op {
+                    Op::Add => Instr::Add,
+                    Op::Sub => Instr::Sub,
+                    Op::Mul => Instr::Mul,
+                    Op::Div => Instr::Div,
+                    other => return Err(CalcError::UnexpectedToken(format!("{other:?}"))),
+                };
+
+                code.push(Instr::Pop(Reg::Ax));
+                code.push(Instr::Pop(Reg::Bx));
+                code.push(alu(Src::Reg(Reg::Ax), Reg::Bx));
+                code.push(Instr::Push(Src::Reg(Reg::Bx)));
+            }
+            // The register machine is a pure arithmetic target; variables and
+            // assignment live only in the direct-tree evaluator.
+            Expr::Var(name) | Expr::Name(name) => {
+                return Err(CalcError::UnexpectedToken(name.clone()))
+            }
+            Expr::Assign => return Err(CalcError::UnexpectedToken("=".to_string())),
+        }
     }
+
+    Ok(code)
+}
+
+/// A small stack-and-register machine that executes compiled bytecode.
+pub struct Machine {
+    registers: [f32; 4],
+    stack: Vec<f32>,
 }
 
-pub fn calculate(expression: &str) -> f32 {
+impl Default for Machine {
+    fn default() -> Machine {
+        Machine::new()
+    }
+}
+
+impl Machine {
+    /// Creates a machine with all registers zeroed and an empty stack.
+    pub fn new() -> Machine {
+        Machine {
+            registers: [0.0; 4],
+            stack: Vec::new(),
+        }
+    }
+
+    /// Reads the current value of a register.
+    fn load(&self, reg: Reg) -> f32 {
+        self.registers[reg as usize]
+    }
+
+    /// Writes a value into a register.
+    fn store(&mut self, reg: Reg, value: f32) {
+        self.registers[reg as usize] = value;
+    }
+
+    /// Resolves a source operand to a concrete value.
+    fn source(&self, src: Src) -> f32 {
+        match src {
+            Src::Reg(reg) => self.load(reg),
+            Src::Imm(imm) => imm,
+        }
+    }
+
+    /// Executes the bytecode and returns the single value left on the stack.
+    pub fn run(&mut self, code: &[Instr]) -> Result<f32, CalcError> {
+        for instr in code {
+            match *instr {
+                Instr::Push(src) => {
+                    let value = self.source(src);
+                    self.stack.push(value);
+                }
+                Instr::Pop(reg) => {
+                    let value = self.stack.pop().ok_or(CalcError::StackUnderflow)?;
+                    self.store(reg, value);
+                }
+                Instr::Add(src, dst) => {
+                    let value = self.load(dst) + self.source(src);
+                    self.store(dst, value);
+                }
+                Instr::Sub(src, dst) => {
+                    let value = self.load(dst) - self.source(src);
+                    self.store(dst, value);
+                }
+                Instr::Mul(src, dst) => {
+                    let value = self.load(dst) * self.source(src);
+                    self.store(dst, value);
+                }
+                Instr::Div(src, dst) => {
+                    let value = self.load(dst) / self.source(src);
+                    self.store(dst, value);
+                }
+            }
+        }
+
+        match self.stack.len() {
+            0 => Err(CalcError::EmptyExpression),
+            1 => Ok(self.stack[0]),
+            _ => Err(CalcError::TrailingOperands),
+        }
+    }
+}
+
+pub fn calculate(expression: &str) -> Result<Value, CalcError> {
     let parser = ExprParser { input: expression };
 
-    let tokens = parser.parse();
+    let tokens = parser.parse()?;
 
     let mut rpn = Rpn::new();
 
     for token in tokens.into_iter() {
-        rpn.push(token);
+        rpn.push(token)?;
     }
 
-    if rpn.0.len() > 1 {
-        return rpn.0[rpn.0.len() - 1];
+    match rpn.stack.len() {
+        0 => Err(CalcError::EmptyExpression),
+        1 => Ok(rpn.stack[0]),
+        _ => Err(CalcError::TrailingOperands),
+    }
+}
+
+/// The process exit status returned when an expression fails to evaluate.
+const EXIT_EVAL_ERROR: i32 = 1;
+
+/// Evaluates a single expression, printing the result or a diagnostic.
+///
+/// Returns `0` on success and [`EXIT_EVAL_ERROR`] on a parse or evaluation
+/// error so callers can propagate it to the process exit status.
+fn run(expression: &str) -> i32 {
+    match calculate(expression) {
+        Ok(value) => {
+            println!("{value}");
+            0
+        }
+        Err(err) => {
+            eprintln!("error: {err}");
+            EXIT_EVAL_ERROR
+        }
     }
+}
 
-    rpn.0[0]
+/// Prints usage information to stdout.
+fn print_usage() {
+    println!("usage: polish-calc [EXPRESSION...]");
+    println!();
+    println!("Evaluate RPN or infix arithmetic expressions.");
+    println!("With no arguments, read one expression per line from stdin.");
+    println!();
+    println!("    echo '1 2 +' | polish-calc");
+    println!("    polish-calc 1 + 2 '*' 3");
+    println!();
+    println!("options:");
+    println!("    -h, --help    show this help and exit");
 }
 
 fn main() {
-    println!("{}", calculate("1 2 +"));
-    println!("{}", calculate("4 2 /"));
-    println!("{}", calculate("1 1 + 2 +"));
-    println!("{}", calculate("1 1 + 1 +"));
-    println!("{}", calculate("1 1 + 1 + 1 +"));
-    println!("{}", calculate("1 1 + 1 + 1 + 1 +"));
-    println!("{}", calculate("1 1 + 1 + 1 + 1 + 1 +"));
-    println!("{}", calculate("1 1 + 1 + 1 + 1 + 1 + 1 +"));
-    println!("{}", calculate("1 1 + 1 + 1 + 1 + 1 + 1 + 1 +"));
-    println!("{}", calculate("1 1 + 1 + 1 + 1 + 1 + 1 + 1 + 1 +"));
+    use std::io::BufRead;
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        print_usage();
+        return;
+    }
+
+    // One-shot mode: treat the arguments as a single expression.
+    if !args.is_empty() {
+        std::process::exit(run(&args.join(" ")));
+    }
+
+    // Filter mode: evaluate one expression per line from stdin.
+    let stdin = std::io::stdin();
+    for line in stdin.lock().lines() {
+        let line = line.expect("failed to read from stdin");
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let status = run(&line);
+        if status != 0 {
+            std::process::exit(status);
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    /// Unwraps a successful numeric calculation, panicking otherwise.
+    fn eval(expression: &str) -> f32 {
+        match calculate(expression).unwrap() {
+            Value::Num(n) => n,
+            other => panic!("expected a number, got {other:?}"),
+        }
+    }
+
+    /// Unwraps a successful boolean calculation, panicking otherwise.
+    fn eval_bool(expression: &str) -> bool {
+        match calculate(expression).unwrap() {
+            Value::Bool(b) => b,
+            other => panic!("expected a boolean, got {other:?}"),
+        }
+    }
+
     #[test]
-    fn test_should_work_for_an_empty_string() {
-        assert!((calculate("") - 0.0).abs() < 1e-7);
+    fn test_should_error_for_an_empty_string() {
+        assert_eq!(calculate(""), Err(CalcError::EmptyExpression));
     }
 
     #[test]
-    fn test_should_parse_numbers() {
-        assert!((calculate("1 2 3") - 3.0).abs() < 1e-7);
+    fn test_should_error_on_trailing_operands() {
+        assert_eq!(calculate("1 2 3"), Err(CalcError::TrailingOperands));
     }
 
     #[test]
-    fn test_should_parse_floats() {
-        assert!((calculate("1 2 3.5") - 3.5).abs() < 1e-7);
+    fn test_should_error_on_undefined_variable() {
+        assert_eq!(
+            calculate("1 foo +"),
+            Err(CalcError::UndefinedVariable("foo".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_should_error_on_stack_underflow() {
+        assert_eq!(calculate("1 +"), Err(CalcError::StackUnderflow));
     }
 
     #[test]
     fn test_should_support_addition() {
-        assert!((calculate("1 3 +") - 4.0).abs() < 1e-7);
+        assert!((eval("1 3 +") - 4.0).abs() < 1e-7);
     }
 
     #[test]
     fn test_should_support_multiplication() {
-        assert!((calculate("1 3 *") - 3.0).abs() < 1e-7);
+        assert!((eval("1 3 *") - 3.0).abs() < 1e-7);
     }
 
     #[test]
     fn test_should_support_subtraction() {
-        assert!((calculate("1 3 -") - -2.0).abs() < 1e-7);
+        assert!((eval("1 3 -") - -2.0).abs() < 1e-7);
     }
 
     #[test]
     fn test_should_support_division() {
-        assert!((calculate("4 2 /") - 2.0).abs() < 1e-7);
+        assert!((eval("4 2 /") - 2.0).abs() < 1e-7);
+    }
+
+    #[test]
+    fn test_should_support_modulo() {
+        assert!((eval("7 3 %") - 1.0).abs() < 1e-7);
+    }
+
+    #[test]
+    fn test_should_support_exponentiation() {
+        assert!((eval("2 10 ^") - 1024.0).abs() < 1e-7);
+    }
+
+    #[test]
+    fn test_should_support_floor_division() {
+        assert!((eval("7 2 //") - 3.0).abs() < 1e-7);
+    }
+
+    #[test]
+    fn test_should_support_variable_assignment() {
+        assert!((eval("3 $x = x x *") - 9.0).abs() < 1e-7);
+    }
+
+    #[test]
+    fn test_should_reuse_variables_across_steps() {
+        assert!((eval("5 $a = a a +") - 10.0).abs() < 1e-7);
+    }
+
+    #[test]
+    fn test_should_support_comparisons() {
+        assert!(eval_bool("3 2 >"));
+        assert!(!eval_bool("2 3 >"));
+    }
+
+    #[test]
+    fn test_should_support_boolean_combinators() {
+        assert!(eval_bool("3 2 > && 1 0 >"));
+        assert!(!eval_bool("1 1 == !"));
+    }
+
+    #[test]
+    fn test_should_compile_and_run_bytecode() {
+        let tokens = ExprParser { input: "1 + 2 * 3" }.parse().unwrap();
+        let code = compile(&tokens).unwrap();
+        assert!((Machine::new().run(&code).unwrap() - 7.0).abs() < 1e-7);
+    }
+
+    #[test]
+    fn test_bytecode_preserves_subtraction_order() {
+        let tokens = ExprParser { input: "10 3 -" }.parse().unwrap();
+        let code = compile(&tokens).unwrap();
+        assert!((Machine::new().run(&code).unwrap() - 7.0).abs() < 1e-7);
+    }
+
+    #[test]
+    fn test_compile_rejects_unsupported_operators() {
+        let tokens = ExprParser { input: "7 3 %" }.parse().unwrap();
+        assert!(matches!(
+            compile(&tokens),
+            Err(CalcError::UnexpectedToken(_))
+        ));
+    }
+
+    #[test]
+    fn test_should_error_on_type_mismatch() {
+        assert!(matches!(calculate("1 2 &&"), Err(CalcError::TypeError(_))));
+    }
+
+    #[test]
+    fn test_should_preserve_chained_rpn_subtraction() {
+        assert!((eval("5 3 - 1 -") - 1.0).abs() < 1e-7);
+    }
+
+    #[test]
+    fn test_should_preserve_chained_rpn_division() {
+        assert!((eval("20 4 / 2 /") - 2.5).abs() < 1e-7);
+    }
+
+    #[test]
+    fn test_should_preserve_chained_rpn_exponentiation() {
+        assert!((eval("2 3 ^ 2 ^") - 64.0).abs() < 1e-7);
+    }
+
+    #[test]
+    fn test_should_preserve_chained_rpn_floor_and_modulo() {
+        assert!((eval("100 7 // 3 //") - 4.0).abs() < 1e-7);
+        assert!((eval("17 10 % 4 %") - 3.0).abs() < 1e-7);
+    }
+
+    #[test]
+    fn test_should_support_infix_precedence() {
+        assert!((eval("1 + 2 * 3") - 7.0).abs() < 1e-7);
+    }
+
+    #[test]
+    fn test_should_support_infix_parentheses() {
+        assert!((eval("( 1 + 2 ) * 3") - 9.0).abs() < 1e-7);
     }
 }
\ No newline at end of file